@@ -0,0 +1,82 @@
+use bevy::{
+    audio::{AudioSink, AudioSinkPlayback, PlaybackSettings},
+    prelude::{
+        AssetServer, AudioBundle, AudioSource, Commands, Component, Event, EventReader, Handle,
+        Input, KeyCode, Query, Res, Resource, With,
+    },
+};
+
+/// Gameplay-decoupled sound cue. Written by the gameplay systems, consumed
+/// by [`play_sfx_on_events`] so those systems never touch asset handles
+/// directly.
+#[derive(Event)]
+pub enum AudioEvent {
+    Score(f32),
+    HitObstacle,
+    Fall,
+    Reset,
+}
+
+#[derive(Resource)]
+struct AudioAssets {
+    score: Handle<AudioSource>,
+    hit_obstacle: Handle<AudioSource>,
+    fall: Handle<AudioSource>,
+    reset: Handle<AudioSource>,
+}
+
+#[derive(Component)]
+struct BackgroundMusic;
+
+pub fn load_audio_assets(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(AudioAssets {
+        score: asset_server.load("audio/score.ogg"),
+        hit_obstacle: asset_server.load("audio/explosion.ogg"),
+        fall: asset_server.load("audio/whoosh.ogg"),
+        reset: asset_server.load("audio/whoosh.ogg"),
+    });
+}
+
+pub fn play_background_music(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        AudioBundle {
+            source: asset_server.load("audio/music.ogg"),
+            settings: PlaybackSettings::LOOP,
+        },
+        BackgroundMusic,
+    ));
+}
+
+pub fn toggle_music(
+    keyboard: Res<Input<KeyCode>>,
+    music_query: Query<&AudioSink, With<BackgroundMusic>>,
+) {
+    if keyboard.just_pressed(KeyCode::M) {
+        if let Ok(sink) = music_query.get_single() {
+            sink.toggle();
+        }
+    }
+}
+
+/// Pitch-scales the score chime by the ball's current speed so a rising
+/// combo sounds faster, then fires the rest of the cues at their base pitch.
+pub fn play_sfx_on_events(
+    mut commands: Commands,
+    mut audio_events: EventReader<AudioEvent>,
+    audio_assets: Res<AudioAssets>,
+) {
+    for event in &mut audio_events {
+        let (source, speed) = match event {
+            AudioEvent::Score(ball_speed) => {
+                (audio_assets.score.clone(), (1. + ball_speed / 50.).clamp(0.5, 3.))
+            }
+            AudioEvent::HitObstacle => (audio_assets.hit_obstacle.clone(), 1.),
+            AudioEvent::Fall => (audio_assets.fall.clone(), 1.),
+            AudioEvent::Reset => (audio_assets.reset.clone(), 1.),
+        };
+        commands.spawn(AudioBundle {
+            source,
+            settings: PlaybackSettings::DESPAWN.with_speed(speed),
+        });
+    }
+}
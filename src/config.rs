@@ -0,0 +1,109 @@
+use std::{
+    fs::File,
+    io::{BufReader, Read, Write},
+};
+
+use bevy::prelude::{Resource, Vec3};
+use serde::{Deserialize, Serialize};
+
+const SETTINGS_PATH: &str = "settings.toml";
+const HIGH_SCORE_PATH: &str = "highscore.txt";
+
+/// Tunables that used to be hard-coded consts, now rebalanceable without a
+/// recompile. Missing or unparseable `settings.toml` falls back to
+/// [`Settings::default`], which matches the game's previous hard-coded
+/// values.
+#[derive(Resource, Deserialize, Serialize, Clone, Copy)]
+pub struct Settings {
+    pub platform_size: [f32; 3],
+    pub slope_angle_degrees: f32,
+    pub camera_offset: [f32; 3],
+    pub ball_control_force: f32,
+    pub obstacle_density_scale: f32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            platform_size: [10., 3., 100.],
+            slope_angle_degrees: 45.,
+            camera_offset: [0., 20., 15.],
+            ball_control_force: 0.5,
+            obstacle_density_scale: 1.,
+        }
+    }
+}
+
+impl Settings {
+    pub fn platform_size(&self) -> Vec3 {
+        Vec3::from_array(self.platform_size)
+    }
+
+    pub fn camera_offset(&self) -> Vec3 {
+        Vec3::from_array(self.camera_offset)
+    }
+}
+
+pub fn load_settings() -> Settings {
+    File::open(SETTINGS_PATH)
+        .ok()
+        .and_then(|file| {
+            let mut contents = String::new();
+            BufReader::new(file).read_to_string(&mut contents).ok()?;
+            toml::from_str(&contents).ok()
+        })
+        .unwrap_or_default()
+}
+
+#[derive(Resource, Default)]
+pub struct HighScore(pub u32);
+
+pub fn load_high_score() -> HighScore {
+    File::open(HIGH_SCORE_PATH)
+        .ok()
+        .and_then(|file| {
+            let mut contents = String::new();
+            BufReader::new(file).read_to_string(&mut contents).ok()?;
+            contents.trim().parse().ok()
+        })
+        .map_or_else(HighScore::default, HighScore)
+}
+
+fn save_high_score(high_score: &HighScore) {
+    if let Ok(mut file) = File::create(HIGH_SCORE_PATH) {
+        let _ = write!(file, "{}", high_score.0);
+    }
+}
+
+/// Compares a just-finished run's score against the saved high score and
+/// writes back the max.
+pub fn update_high_score(high_score: &mut HighScore, final_score: u32) {
+    if final_score > high_score.0 {
+        high_score.0 = final_score;
+        save_high_score(high_score);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_default_matches_hardcoded_fallback() {
+        let settings = Settings::default();
+        assert_eq!(settings.platform_size, [10., 3., 100.]);
+        assert_eq!(settings.slope_angle_degrees, 45.);
+        assert_eq!(settings.camera_offset, [0., 20., 15.]);
+        assert_eq!(settings.ball_control_force, 0.5);
+        assert_eq!(settings.obstacle_density_scale, 1.);
+    }
+
+    #[test]
+    fn update_high_score_only_keeps_the_max() {
+        let mut high_score = HighScore(10);
+        update_high_score(&mut high_score, 5);
+        assert_eq!(high_score.0, 10);
+        update_high_score(&mut high_score, 20);
+        assert_eq!(high_score.0, 20);
+    }
+}
@@ -1,30 +1,47 @@
 #![warn(clippy::pedantic, clippy::nursery)]
 #![allow(clippy::needless_pass_by_value, clippy::cast_precision_loss)]
 
+mod audio;
+mod config;
+mod netcode;
+mod sky;
+mod track;
+
 use bevy::{
     core_pipeline::{bloom::BloomSettings, tonemapping::Tonemapping},
-    math::vec3,
     prelude::{
-        shape::{Box, Icosphere},
-        App, AssetServer, Assets, Camera, Camera3dBundle, ClearColor, Color, Commands, Component,
-        Entity, Event, EventReader, EventWriter, FixedUpdate, Input, KeyCode, Mesh, PbrBundle,
-        PluginGroup, Query, Res, ResMut, StandardMaterial, Startup, TextBundle, Transform, Vec3,
-        With, Without,
+        in_state, not, resource_exists, shape::{Box, Icosphere}, App, AssetServer, Assets,
+        Camera, Camera3dBundle, ClearColor, Color, Commands, Component, Entity, EventReader,
+        EventWriter, FixedUpdate, Input, IntoSystemConfigs, KeyCode, Mesh, NextState, OnEnter,
+        OnExit, PbrBundle, PluginGroup, Query, Res, ResMut, Resource, State, StandardMaterial,
+        States, Startup, TextBundle, Time, Timer, TimerMode, Transform, Update, Vec3, With,
+        Without,
     },
     text::{Text, TextAlignment, TextStyle},
-    ui::{Style, UiRect, Val},
+    ui::{PositionType, Style, UiRect, Val},
     window::{Window, WindowPlugin},
     DefaultPlugins,
 };
+use bevy_ggrs::{AddRollbackCommandExtension, GgrsPlugin, GgrsSchedule, ReadInputs, Session};
 use bevy_rapier3d::{
     prelude::{
-        ActiveEvents, Collider, CollisionEvent, NoUserData, RapierPhysicsPlugin, RigidBody,
-        Velocity,
+        ActiveEvents, Collider, CollisionEvent, NoUserData, RapierConfiguration,
+        RapierPhysicsPlugin, RigidBody, Velocity,
     },
     render::RapierDebugRenderPlugin,
 };
+use netcode::{GgrsConfig, Player};
 use rand::random;
 
+#[derive(States, Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+enum AppState {
+    #[default]
+    Menu,
+    Playing,
+    Paused,
+    GameOver,
+}
+
 #[derive(Component)]
 struct Ball;
 
@@ -45,21 +62,79 @@ struct Scored(bool);
 #[derive(Component)]
 struct ScoreText;
 
+#[derive(Component)]
+struct BestScoreText;
+
 #[derive(Component)]
 struct Obstacle;
 
-#[derive(Event, Default)]
-struct ResetEvent;
+#[derive(Component)]
+struct Debris;
 
-const PLATFORM_SIZE: Vec3 = vec3(10., 3., 100.);
-const CAMERA_OFFSET: Vec3 = vec3(0., 20., 15.);
+/// Counts down to zero, then the entity despawns. Drives the debris burst
+/// spawned in [`detect_hit_obstacle`].
+#[derive(Component)]
+struct Lifetime(Timer);
 
-fn setup_scene(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    asset_server: Res<AssetServer>,
-) {
+/// How many fixed-update ticks the debris burst plays for before
+/// [`resolve_pending_game_over`] actually ends the run.
+const GAME_OVER_DELAY_TICKS: u32 = 20;
+
+/// Per-run simulation counters that every peer's rollback resimulation must
+/// agree on. Kept as a `Component` on a single dedicated entity rather than
+/// a `Resource`: `bevy_ggrs` only snapshots and restores rollback
+/// components, so a `Resource` mutated inside `GgrsSchedule` would keep
+/// whatever value a discarded speculative resimulation last left it at
+/// instead of rolling back with everything else.
+#[derive(Component, Clone, Copy, Default)]
+struct NetGameState {
+    /// How many platform pairs have been spawned; the noise field's
+    /// sampling position, so it must match across peers or the generated
+    /// track diverges.
+    track_progress: u32,
+    /// Set by [`detect_hit_obstacle`] and ticked down by
+    /// [`resolve_pending_game_over`] while a hit's debris burst plays out.
+    pending_game_over_ticks: Option<u32>,
+    /// Raised once the run should end. [`apply_game_over_transition`] reads
+    /// this outside `GgrsSchedule` to actually fire the `AppState` change.
+    game_over: bool,
+}
+
+/// Present while the camera should be jittering from an impact. Counts down
+/// and decays in [`move_camera_to_ball`].
+#[derive(Resource)]
+struct CameraShake {
+    ticks_remaining: u32,
+    magnitude: f32,
+}
+
+#[derive(Component)]
+struct MenuText;
+
+#[derive(Component)]
+struct PausedText;
+
+#[derive(Component)]
+struct GameOverText;
+
+#[derive(Resource, Default)]
+struct FinalScore(u32);
+
+/// How many balls `spawn_game` creates. `1` outside of a GGRS session; one per
+/// entry in [`netcode::NetcodeArgs::players`] when racing.
+#[derive(Resource)]
+struct NumPlayers(usize);
+
+/// Marks that `spawn_game` has already populated the world for the run in
+/// progress. `Playing` is entered both to start a new run (from `Menu` or
+/// `GameOver`) and to resume one (from `Paused`); gating `spawn_game` on the
+/// absence of this resource keeps a resume from spawning a second set of
+/// balls/platforms on top of the paused ones.
+#[derive(Resource)]
+struct ActiveRun;
+
+fn setup_camera(mut commands: Commands, settings: Res<config::Settings>) {
+    let camera_offset = settings.camera_offset();
     commands.spawn((
         Camera3dBundle {
             camera: Camera {
@@ -67,50 +142,151 @@ fn setup_scene(
                 ..Default::default()
             },
             tonemapping: Tonemapping::TonyMcMapface,
-            transform: Transform::from_translation(CAMERA_OFFSET).looking_at(Vec3::ZERO, Vec3::Y),
+            transform: Transform::from_translation(camera_offset).looking_at(Vec3::ZERO, Vec3::Y),
             ..Default::default()
         },
         BloomSettings::default(),
         MainCamera {
-            offset_from_target: CAMERA_OFFSET,
+            offset_from_target: camera_offset,
         },
     ));
+}
+
+fn prompt_text_bundle(contents: impl Into<String>, asset_server: &AssetServer) -> TextBundle {
+    TextBundle::from_section(
+        contents,
+        TextStyle {
+            font: asset_server.load("Fira Code Retina.ttf"),
+            font_size: 50.,
+            color: Color::GREEN,
+        },
+    )
+    .with_text_alignment(TextAlignment::Center)
+    .with_style(Style {
+        margin: UiRect::horizontal(Val::Auto),
+        ..Default::default()
+    })
+}
+
+fn spawn_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
     commands.spawn((
-        PbrBundle {
-            mesh: meshes.add(
-                Mesh::try_from(Icosphere {
-                    radius: 1.,
-                    subdivisions: 3,
-                })
-                .unwrap(),
+        prompt_text_bundle("press enter to play", &asset_server),
+        MenuText,
+    ));
+}
+
+fn despawn_menu(mut commands: Commands, menu_text_query: Query<Entity, With<MenuText>>) {
+    for entity in &menu_text_query {
+        commands.entity(entity).despawn();
+    }
+}
+
+fn spawn_paused_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        prompt_text_bundle("paused - press escape to resume", &asset_server),
+        PausedText,
+    ));
+}
+
+fn despawn_paused_text(mut commands: Commands, paused_text_query: Query<Entity, With<PausedText>>) {
+    for entity in &paused_text_query {
+        commands.entity(entity).despawn();
+    }
+}
+
+fn spawn_game_over_text(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    final_score: Res<FinalScore>,
+) {
+    commands.spawn((
+        prompt_text_bundle(
+            format!(
+                "score: {}\npress enter to restart",
+                final_score.0
             ),
-            material: materials.add(StandardMaterial {
-                emissive: Color::rgb_linear(0., 2., 0.),
-                ..Default::default()
-            }),
-            ..Default::default()
-        },
-        Ball,
-        RigidBody::Dynamic,
-        Velocity::zero(),
-        Collider::ball(1.),
-        Score(0),
-        ActiveEvents::COLLISION_EVENTS,
+            &asset_server,
+        ),
+        GameOverText,
     ));
+}
+
+fn despawn_game_over_text(
+    mut commands: Commands,
+    game_over_text_query: Query<Entity, With<GameOverText>>,
+) {
+    for entity in &game_over_text_query {
+        commands.entity(entity).despawn();
+    }
+}
+
+fn spawn_game(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    asset_server: Res<AssetServer>,
+    mut camera_query: Query<&mut Transform, With<MainCamera>>,
+    num_players: Res<NumPlayers>,
+    mut audio_events: EventWriter<audio::AudioEvent>,
+    settings: Res<config::Settings>,
+    high_score: Res<config::HighScore>,
+    session: Option<Res<Session<GgrsConfig>>>,
+) {
+    commands.insert_resource(ActiveRun);
+    let platform_size = settings.platform_size();
+    *camera_query.single_mut() =
+        Transform::from_translation(settings.camera_offset()).looking_at(Vec3::ZERO, Vec3::Y);
+    audio_events.send(audio::AudioEvent::Reset);
+    let mut net_state = commands.spawn(NetGameState::default());
+    if session.is_some() {
+        net_state.add_rollback();
+    }
+    for handle in 0..num_players.0 {
+        let mut ball = commands.spawn((
+            PbrBundle {
+                mesh: meshes.add(
+                    Mesh::try_from(Icosphere {
+                        radius: 1.,
+                        subdivisions: 3,
+                    })
+                    .unwrap(),
+                ),
+                transform: Transform::from_translation(Vec3::new(handle as f32 * 5., 0., 0.)),
+                material: materials.add(StandardMaterial {
+                    emissive: Color::rgb_linear(0., 2., 0.),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            Ball,
+            Player(handle),
+            RigidBody::Dynamic,
+            Velocity::zero(),
+            Collider::ball(1.),
+            Score(0),
+            ActiveEvents::COLLISION_EVENTS,
+        ));
+        // Only GGRS races need their balls' Transform/Velocity/Score
+        // snapshotted for rollback; tagging them in single-player would
+        // panic looking up a RollbackIdProvider that was never inserted.
+        if session.is_some() {
+            ball.add_rollback();
+        }
+    }
     commands.spawn((
         PbrBundle {
             mesh: meshes.add(Mesh::from(Box {
-                max_x: PLATFORM_SIZE.x / 2.,
-                max_y: PLATFORM_SIZE.y / 2.,
-                max_z: PLATFORM_SIZE.z / 2.,
-                min_x: -PLATFORM_SIZE.x / 2.,
-                min_y: -PLATFORM_SIZE.y / 2.,
-                min_z: -PLATFORM_SIZE.z / 2.,
+                max_x: platform_size.x / 2.,
+                max_y: platform_size.y / 2.,
+                max_z: platform_size.z / 2.,
+                min_x: -platform_size.x / 2.,
+                min_y: -platform_size.y / 2.,
+                min_z: -platform_size.z / 2.,
             })),
             transform: {
                 let mut transform =
-                    Transform::from_translation(Vec3::new(0., -10. - PLATFORM_SIZE.y / 2., 0.));
-                transform.rotate_axis(Vec3::X, -45_f32.to_radians());
+                    Transform::from_translation(Vec3::new(0., -10. - platform_size.y / 2., 0.));
+                transform.rotate_axis(Vec3::X, -settings.slope_angle_degrees.to_radians());
                 transform
             },
             material: materials.add(Color::BLACK.into()),
@@ -118,9 +294,9 @@ fn setup_scene(
         },
         Platform,
         Collider::cuboid(
-            PLATFORM_SIZE.x / 2.,
-            PLATFORM_SIZE.y / 2.,
-            PLATFORM_SIZE.z / 2.,
+            platform_size.x / 2.,
+            platform_size.y / 2.,
+            platform_size.z / 2.,
         ),
         Scored(false),
     ));
@@ -140,28 +316,124 @@ fn setup_scene(
         }),
         ScoreText,
     ));
+    commands.spawn((
+        TextBundle::from_section(
+            format!("best: {}", high_score.0),
+            TextStyle {
+                font: asset_server.load("Fira Code Retina.ttf"),
+                font_size: 30.,
+                color: Color::GREEN,
+            },
+        )
+        .with_text_alignment(TextAlignment::Center)
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.),
+            right: Val::Px(10.),
+            ..Default::default()
+        }),
+        BestScoreText,
+    ));
+}
+
+fn despawn_game(
+    mut commands: Commands,
+    ball_query: Query<Entity, With<Ball>>,
+    platform_query: Query<Entity, With<Platform>>,
+    obstacle_query: Query<Entity, With<Obstacle>>,
+    debris_query: Query<Entity, With<Debris>>,
+    net_state_query: Query<Entity, With<NetGameState>>,
+    score_text_query: Query<Entity, With<ScoreText>>,
+    best_score_text_query: Query<Entity, With<BestScoreText>>,
+    score_query: Query<(&Score, &Player)>,
+    mut final_score: ResMut<FinalScore>,
+    mut high_score: ResMut<config::HighScore>,
+) {
+    final_score.0 = score_query
+        .iter()
+        .find(|(_, player)| player.0 == 0)
+        .map_or(0, |(score, _)| score.0);
+    config::update_high_score(&mut high_score, final_score.0);
+    commands.remove_resource::<ActiveRun>();
+    commands.remove_resource::<CameraShake>();
+    for entity in &ball_query {
+        commands.entity(entity).despawn();
+    }
+    for entity in &platform_query {
+        commands.entity(entity).despawn();
+    }
+    for entity in &obstacle_query {
+        commands.entity(entity).despawn();
+    }
+    for entity in &debris_query {
+        commands.entity(entity).despawn();
+    }
+    for entity in &net_state_query {
+        commands.entity(entity).despawn();
+    }
+    for entity in &score_text_query {
+        commands.entity(entity).despawn();
+    }
+    for entity in &best_score_text_query {
+        commands.entity(entity).despawn();
+    }
 }
 
+/// Local, non-rollback input handling used when no GGRS [`Session`] is
+/// running. Networked races instead read `netcode::INPUT_LEFT`/
+/// `netcode::INPUT_RIGHT` out of `bevy_ggrs`'s `PlayerInputs` inside
+/// [`GgrsSchedule`].
 fn handle_input(
-    mut ball_query: Query<(&mut Transform, &mut Velocity, &mut Score), With<Ball>>,
+    mut ball_query: Query<(&mut Velocity, &Player), With<Ball>>,
     keyboard: Res<Input<KeyCode>>,
-    mut event_writer: EventWriter<ResetEvent>,
+    settings: Res<config::Settings>,
 ) {
     let horizontal = f32::from(keyboard.any_pressed([KeyCode::Right, KeyCode::D]))
         - f32::from(keyboard.any_pressed([KeyCode::Left, KeyCode::A]));
-    ball_query.single_mut().1.linvel.x += horizontal * 0.5;
-    if keyboard.pressed(KeyCode::R) {
-        event_writer.send_default();
+    if let Some((mut velocity, _)) = ball_query.iter_mut().find(|(_, player)| player.0 == 0) {
+        velocity.linvel.x += horizontal * settings.ball_control_force;
     }
 }
 
+fn transition_app_state(
+    keyboard: Res<Input<KeyCode>>,
+    app_state: Res<State<AppState>>,
+    mut next_app_state: ResMut<NextState<AppState>>,
+) {
+    match app_state.get() {
+        AppState::Menu | AppState::GameOver if keyboard.just_pressed(KeyCode::Return) => {
+            next_app_state.set(AppState::Playing);
+        }
+        AppState::Playing if keyboard.just_pressed(KeyCode::Escape) => {
+            next_app_state.set(AppState::Paused);
+        }
+        AppState::Paused if keyboard.just_pressed(KeyCode::Escape) => {
+            next_app_state.set(AppState::Playing);
+        }
+        AppState::Menu | AppState::Playing | AppState::Paused | AppState::GameOver => {}
+    }
+}
+
+/// Freezes the physics simulation while the game isn't actively `Playing`,
+/// so a paused ball doesn't keep falling/rolling underneath the "paused"
+/// overlay.
+fn toggle_physics(app_state: Res<State<AppState>>, mut rapier_config: ResMut<RapierConfiguration>) {
+    rapier_config.physics_pipeline_active = *app_state.get() == AppState::Playing;
+}
+
 fn increase_score_and_spawn_platforms(
-    mut ball_query: Query<(&mut Score, &Transform)>,
+    mut ball_query: Query<(&mut Score, &Transform, &Velocity, &Player)>,
     mut platform_query: Query<(&mut Scored, &Transform)>,
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
+    mut audio_events: EventWriter<audio::AudioEvent>,
+    track_generator: Res<track::TrackGenerator>,
+    mut net_state_query: Query<&mut NetGameState>,
+    settings: Res<config::Settings>,
+    session: Option<Res<Session<GgrsConfig>>>,
 ) {
+    let platform_size = settings.platform_size();
     let mut last_platform = platform_query.iter_mut().collect::<Vec<_>>();
     last_platform.sort_unstable_by(|(_, first), (_, second)| {
         first
@@ -171,32 +443,66 @@ fn increase_score_and_spawn_platforms(
             .reverse()
     });
     let last_platform = last_platform.last_mut().unwrap();
-    if ball_query.single().1.translation.z < last_platform.1.translation.z {
+    let frontrunner_z = ball_query
+        .iter()
+        .map(|(_, transform, _, _)| transform.translation.z)
+        .fold(f32::INFINITY, f32::min);
+    let threshold_z = last_platform.1.translation.z;
+    if frontrunner_z < threshold_z {
         let (ref mut scored, _transform) = last_platform;
+        for (mut score, transform, velocity, player) in &mut ball_query {
+            if transform.translation.z < threshold_z {
+                score.0 += 1;
+                if player.0 == 0 {
+                    audio_events.send(audio::AudioEvent::Score(velocity.linvel.length()));
+                }
+            }
+        }
         if !scored.0 {
-            let (mut score, ball_transform) = ball_query.single_mut();
-            score.0 += 1;
+            let ball_transform = Transform::from_translation(
+                ball_query
+                    .iter()
+                    .map(|(_, transform, _, _)| transform.translation)
+                    .reduce(|frontmost, translation| {
+                        if translation.z < frontmost.z {
+                            translation
+                        } else {
+                            frontmost
+                        }
+                    })
+                    .unwrap(),
+            );
+            let highest_score = ball_query
+                .iter()
+                .map(|(score, ..)| score.0)
+                .max()
+                .unwrap_or(0);
+            let mut net_state = net_state_query.single_mut();
+            let distance = f64::from(net_state.track_progress);
+            let (lateral_offset, bank_angle) = track_generator.path_offset(distance, highest_score);
+            let density = track_generator.obstacle_density(distance);
+            net_state.track_progress += 1;
             for index in 0..2 {
                 let transform = {
                     let mut transform = Transform::from_translation(Vec3::new(
-                        random::<f32>().mul_add(20., (index as f32).mul_add(20., -10.) - 10.)
+                        lateral_offset + (index as f32).mul_add(20., -10.)
                             + ball_transform.translation.x,
                         ball_transform.translation.y - 90.,
                         ball_transform.translation.z - 80.,
                     ));
-                    transform.rotate_axis(Vec3::X, -45_f32.to_radians());
-                    transform.rotate_axis(Vec3::Z, random::<f32>().mul_add(20., -10.).to_radians());
+                    transform.rotate_axis(Vec3::X, -settings.slope_angle_degrees.to_radians());
+                    transform.rotate_axis(Vec3::Z, bank_angle);
                     transform
                 };
-                commands.spawn((
+                let mut platform = commands.spawn((
                     PbrBundle {
                         mesh: meshes.add(Mesh::from(Box {
-                            max_x: PLATFORM_SIZE.x / 2.,
-                            max_y: PLATFORM_SIZE.y / 2.,
-                            max_z: PLATFORM_SIZE.z / 2.,
-                            min_x: -PLATFORM_SIZE.x / 2.,
-                            min_y: -PLATFORM_SIZE.y / 2.,
-                            min_z: -PLATFORM_SIZE.z / 2.,
+                            max_x: platform_size.x / 2.,
+                            max_y: platform_size.y / 2.,
+                            max_z: platform_size.z / 2.,
+                            min_x: -platform_size.x / 2.,
+                            min_y: -platform_size.y / 2.,
+                            min_z: -platform_size.z / 2.,
                         })),
                         transform,
                         material: materials.add(Color::BLACK.into()),
@@ -204,15 +510,32 @@ fn increase_score_and_spawn_platforms(
                     },
                     Platform,
                     Collider::cuboid(
-                        PLATFORM_SIZE.x / 2.,
-                        PLATFORM_SIZE.y / 2.,
-                        PLATFORM_SIZE.z / 2.,
+                        platform_size.x / 2.,
+                        platform_size.y / 2.,
+                        platform_size.z / 2.,
                     ),
                     Scored(false),
                 ));
+                // Spawned here, inside `GgrsSchedule` when racing: without
+                // `add_rollback` these would duplicate on every rollback
+                // resimulation, since bevy_ggrs only restores entities it
+                // tracks and a plain `Commands::spawn` has no memory of the
+                // discarded timeline.
+                if session.is_some() {
+                    platform.add_rollback();
+                }
+                let platform_seed = distance + f64::from(index) * 3000.;
                 #[allow(clippy::cast_possible_truncation)]
-                for _ in 0..random::<f32>().mul_add(2., 2.) as i32 {
-                    commands.spawn((
+                for obstacle_index in 0..(density * settings.obstacle_density_scale).mul_add(4., 1.) as i32 {
+                    let forward_jitter = (track_generator
+                        .jitter(platform_seed, f64::from(obstacle_index))
+                        + 1.)
+                        / 2.;
+                    let right_jitter = (track_generator
+                        .jitter(platform_seed + 1000., f64::from(obstacle_index))
+                        + 1.)
+                        / 2.;
+                    let mut obstacle = commands.spawn((
                         PbrBundle {
                             mesh: meshes.add(
                                 Mesh::try_from(Icosphere {
@@ -225,11 +548,11 @@ fn increase_score_and_spawn_platforms(
                                 transform.translation
                                     + transform.up() * 3.
                                     + transform.forward()
-                                        * random::<f32>()
-                                            .mul_add(PLATFORM_SIZE.z, -PLATFORM_SIZE.z / 2.)
+                                        * forward_jitter
+                                            .mul_add(platform_size.z, -platform_size.z / 2.)
                                     + transform.right()
-                                        * random::<f32>()
-                                            .mul_add(PLATFORM_SIZE.x, -PLATFORM_SIZE.x / 2.),
+                                        * right_jitter
+                                            .mul_add(platform_size.x, -platform_size.x / 2.),
                             ),
                             material: materials.add(StandardMaterial {
                                 emissive: Color::rgb_linear(2., 0., 0.),
@@ -240,6 +563,9 @@ fn increase_score_and_spawn_platforms(
                         Obstacle,
                         Collider::ball(1.),
                     ));
+                    if session.is_some() {
+                        obstacle.add_rollback();
+                    }
                 }
             }
         }
@@ -247,19 +573,28 @@ fn increase_score_and_spawn_platforms(
     }
 }
 
-fn update_score(score_query: Query<&Score>, mut text_query: Query<&mut Text, With<ScoreText>>) {
-    let score = score_query.single().0;
-    text_query.single_mut().sections[0].value = score.to_string();
+fn update_score(
+    score_query: Query<(&Score, &Player)>,
+    mut text_query: Query<&mut Text, With<ScoreText>>,
+) {
+    let Some((score, _)) = score_query.iter().find(|(_, player)| player.0 == 0) else {
+        return;
+    };
+    text_query.single_mut().sections[0].value = score.0.to_string();
 }
 
 type CameraData<'a> = (&'a mut Transform, &'a MainCamera);
 type CameraFilter = (With<MainCamera>, Without<Ball>, Without<Scored>);
 
 fn move_camera_to_ball(
-    ball_query: Query<&Transform, With<Ball>>,
+    ball_query: Query<(&Transform, &Player), With<Ball>>,
     mut camera_query: Query<CameraData, CameraFilter>,
+    mut camera_shake: Option<ResMut<CameraShake>>,
+    mut commands: Commands,
 ) {
-    let ball_position = ball_query.single();
+    let Some((ball_position, _)) = ball_query.iter().find(|(_, player)| player.0 == 0) else {
+        return;
+    };
     let (mut transform, main_camera) = camera_query.single_mut();
     let distance_error = ((ball_position.translation - transform.translation).length()
         - main_camera.offset_from_target.length())
@@ -272,71 +607,205 @@ fn move_camera_to_ball(
         .looking_at(ball_position.translation, Vec3::Y)
         .rotation;
     transform.rotation = transform.rotation.slerp(target, 0.05);
+
+    if let Some(camera_shake) = camera_shake.as_mut() {
+        if camera_shake.ticks_remaining == 0 {
+            commands.remove_resource::<CameraShake>();
+        } else {
+            let falloff = camera_shake.ticks_remaining as f32 / GAME_OVER_DELAY_TICKS as f32;
+            transform.translation += Vec3::new(
+                random::<f32>() - 0.5,
+                random::<f32>() - 0.5,
+                random::<f32>() - 0.5,
+            ) * camera_shake.magnitude
+                * falloff;
+            camera_shake.ticks_remaining -= 1;
+        }
+    }
 }
 
+/// Ends the run when the locally-displayed ball (`Player(0)`) falls off the
+/// track. A remote racer falling behind in a GGRS session doesn't end the
+/// local player's run.
 fn detect_fall(
-    mut ball_query: Query<&mut Transform, With<Ball>>,
+    ball_query: Query<(&Transform, &Player), With<Ball>>,
     platform_query: Query<(&mut Scored, &Transform, Entity), Without<Ball>>,
-    mut event_writer: EventWriter<ResetEvent>,
+    mut net_state_query: Query<&mut NetGameState>,
+    mut audio_events: EventWriter<audio::AudioEvent>,
 ) {
     let minimum = platform_query
         .iter()
         .map(|platform| platform.1.translation.y)
         .reduce(f32::min)
         .unwrap();
-    let transform = ball_query.single_mut();
+    let Some((transform, _)) = ball_query.iter().find(|(_, player)| player.0 == 0) else {
+        return;
+    };
     if transform.translation.y < minimum - 20. {
-        event_writer.send_default();
+        audio_events.send(audio::AudioEvent::Fall);
+        net_state_query.single_mut().game_over = true;
     }
 }
 
-fn reset(
-    mut ball_query: Query<(&mut Transform, &mut Velocity, &mut Score), With<Ball>>,
-    mut platform_query: Query<(&mut Scored, &Transform, Entity), Without<Ball>>,
-    mut camera_query: Query<&mut Transform, CameraFilter>,
-    mut commands: Commands,
+/// Instead of ending the run on the spot, spawns a speed-scaled debris burst
+/// and a decaying [`CameraShake`], then leaves [`NetGameState::pending_game_over_ticks`]
+/// for [`resolve_pending_game_over`] to act on once the burst has had time to
+/// read.
+fn detect_hit_obstacle(
+    mut collision_events: EventReader<CollisionEvent>,
+    ball_query: Query<(Entity, &Transform, &Velocity, &Player), With<Ball>>,
     obstacle_query: Query<Entity, With<Obstacle>>,
-    event_reader: EventReader<ResetEvent>,
+    mut net_state_query: Query<&mut NetGameState>,
+    track_generator: Res<track::TrackGenerator>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut audio_events: EventWriter<audio::AudioEvent>,
+    session: Option<Res<Session<GgrsConfig>>>,
 ) {
-    if event_reader.is_empty() {
+    let mut net_state = net_state_query.single_mut();
+    if net_state.pending_game_over_ticks.is_some() {
         return;
     }
-    let (mut transform, mut velocity, mut score) = ball_query.single_mut();
-    *transform = Transform::default();
-    *velocity = Velocity::zero();
-    score.0 = 0;
-    for platform in platform_query.iter().skip(1) {
-        commands.entity(platform.2).despawn();
+    for event in &mut collision_events {
+        let CollisionEvent::Started(first, second, _) = event else {
+            continue;
+        };
+        let Some((_, transform, velocity, _)) = ball_query
+            .iter()
+            .find(|(entity, _, _, player)| *entity == *first && player.0 == 0)
+        else {
+            continue;
+        };
+        if !obstacle_query.contains(*second) {
+            continue;
+        }
+        audio_events.send(audio::AudioEvent::HitObstacle);
+        let speed = velocity.linvel.length();
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let debris_count = speed.mul_add(0.5, 6.) as u32;
+        let debris_mesh = meshes.add(
+            Mesh::try_from(Icosphere {
+                radius: 0.2,
+                subdivisions: 0,
+            })
+            .unwrap(),
+        );
+        let debris_material = materials.add(StandardMaterial {
+            emissive: Color::rgb_linear(2., 1., 0.),
+            ..Default::default()
+        });
+        for debris_index in 0..debris_count {
+            // Derived from the ball's (rollback-restored) position instead of
+            // `rand::random()` so every peer replays the same burst shape.
+            let seed = f64::from(transform.translation.x)
+                + f64::from(transform.translation.z)
+                + f64::from(debris_index);
+            let direction = Vec3::new(
+                track_generator.jitter(seed, 0.),
+                track_generator.jitter(seed, 1.),
+                track_generator.jitter(seed, 2.),
+            )
+            .normalize_or_zero();
+            let mut debris = commands.spawn((
+                PbrBundle {
+                    mesh: debris_mesh.clone(),
+                    transform: Transform::from_translation(transform.translation),
+                    material: debris_material.clone(),
+                    ..Default::default()
+                },
+                Debris,
+                RigidBody::Dynamic,
+                Velocity::linear(direction * speed.mul_add(0.3, 5.)),
+                Collider::ball(0.2),
+                Lifetime(Timer::from_seconds(0.4, TimerMode::Once)),
+            ));
+            // Same rollback-duplication hazard as the platforms/obstacles
+            // spawned in `increase_score_and_spawn_platforms`.
+            if session.is_some() {
+                debris.add_rollback();
+            }
+        }
+        net_state.pending_game_over_ticks = Some(GAME_OVER_DELAY_TICKS);
+        commands.insert_resource(CameraShake {
+            ticks_remaining: GAME_OVER_DELAY_TICKS,
+            magnitude: speed.mul_add(0.05, 0.5),
+        });
+        return;
     }
-    for mut platform in &mut platform_query {
-        *platform.0 = Scored(false);
+}
+
+/// Ticks down the pending-game-over countdown left by [`detect_hit_obstacle`],
+/// only raising [`NetGameState::game_over`] once the debris burst has played
+/// out. Leaves the actual `AppState` transition to
+/// [`apply_game_over_transition`] outside `GgrsSchedule`.
+fn resolve_pending_game_over(mut net_state_query: Query<&mut NetGameState>) {
+    let mut net_state = net_state_query.single_mut();
+    let Some(ticks_remaining) = net_state.pending_game_over_ticks else {
+        return;
+    };
+    if ticks_remaining == 0 {
+        net_state.pending_game_over_ticks = None;
+        net_state.game_over = true;
+    } else {
+        net_state.pending_game_over_ticks = Some(ticks_remaining - 1);
     }
-    for obstacle in &obstacle_query {
-        commands.entity(obstacle).despawn();
+}
+
+/// Applies the `AppState::GameOver` transition flagged by [`detect_fall`] or
+/// [`resolve_pending_game_over`]. Kept out of `GgrsSchedule` entirely: a
+/// `NextState` transition isn't part of the rollback-tracked world, so
+/// firing it from a system GGRS might resimulate could trigger it more than
+/// once and tear down rollback-registered `Ball` entities (via
+/// `OnEnter(GameOver) -> despawn_game`) out from under an in-flight rollback.
+fn apply_game_over_transition(
+    net_state_query: Query<&NetGameState>,
+    mut next_app_state: ResMut<NextState<AppState>>,
+) {
+    let Ok(net_state) = net_state_query.get_single() else {
+        return;
+    };
+    if net_state.game_over {
+        next_app_state.set(AppState::GameOver);
     }
-    *camera_query.single_mut() =
-        Transform::from_translation(CAMERA_OFFSET).looking_at(Vec3::ZERO, Vec3::Y);
 }
 
-fn detect_hit_obstacle(
-    mut collision_events: EventReader<CollisionEvent>,
-    ball_query: Query<(Entity, &mut Transform, &mut Velocity, &mut Score), With<Ball>>,
-    obstacle_query: Query<Entity, With<Obstacle>>,
-    mut event_writer: EventWriter<ResetEvent>,
+fn despawn_expired_debris(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut debris_query: Query<(Entity, &mut Lifetime)>,
 ) {
-    for event in &mut collision_events {
-        if let CollisionEvent::Started(first, second, _) = event {
-            if ball_query.single().0 == *first && obstacle_query.contains(*second) {
-                event_writer.send_default();
-            }
+    for (entity, mut lifetime) in &mut debris_query {
+        lifetime.0.tick(time.delta());
+        if lifetime.0.finished() {
+            commands.entity(entity).despawn();
         }
     }
 }
 
 fn main() {
-    App::new()
-        .insert_resource(ClearColor(Color::BLACK))
-        .add_event::<ResetEvent>()
+    let netcode_args = netcode::parse_cli();
+    let num_players = netcode_args.as_ref().map_or(1, |args| args.players.len());
+    // Racing needs physics to be part of the deterministic, rollback-aware
+    // `GgrsSchedule` instead of the regular, non-rollback `FixedUpdate`.
+    // TODO: cross-platform determinism also needs bevy_rapier3d's
+    // `enhanced-determinism` feature turned on in Cargo.toml. This checkout
+    // doesn't have a manifest to add it to, so it isn't enabled yet.
+    let mut physics_plugin = RapierPhysicsPlugin::<NoUserData>::default();
+    if netcode_args.is_some() {
+        physics_plugin = physics_plugin.in_schedule(GgrsSchedule);
+    }
+
+    let mut app = App::new();
+    app.insert_resource(ClearColor(Color::BLACK))
+        .init_resource::<FinalScore>()
+        .init_resource::<sky::SkySettings>()
+        .init_resource::<track::TrackSeed>()
+        .insert_resource(config::load_settings())
+        .insert_resource(config::load_high_score())
+        .insert_resource(NumPlayers(num_players))
+        .add_state::<AppState>()
+        .add_event::<audio::AudioEvent>()
         .add_plugins((
             DefaultPlugins.set(WindowPlugin {
                 primary_window: Some(Window {
@@ -345,21 +814,97 @@ fn main() {
                 }),
                 ..Default::default()
             }),
-            RapierPhysicsPlugin::<NoUserData>::default(),
+            physics_plugin,
             RapierDebugRenderPlugin::default(),
         ))
-        .add_systems(Startup, setup_scene)
         .add_systems(
-            FixedUpdate,
+            Startup,
+            (setup_camera, sky::load_skybox, sky::attach_skybox_to_camera).chain(),
+        )
+        .add_systems(
+            Startup,
+            (audio::load_audio_assets, audio::play_background_music),
+        )
+        .add_systems(Startup, track::build_track_generator)
+        .add_systems(Update, sky::reinterpret_skybox_once_loaded)
+        .add_systems(
+            Update,
+            sky::update_gradient_sky.run_if(in_state(AppState::Playing)),
+        )
+        .add_systems(Update, (audio::toggle_music, audio::play_sfx_on_events))
+        .add_systems(OnEnter(AppState::Menu), spawn_menu)
+        .add_systems(OnExit(AppState::Menu), despawn_menu)
+        .add_systems(OnEnter(AppState::Paused), spawn_paused_text)
+        .add_systems(OnExit(AppState::Paused), despawn_paused_text)
+        .add_systems(
+            OnEnter(AppState::GameOver),
+            (despawn_game, spawn_game_over_text).chain(),
+        )
+        .add_systems(OnExit(AppState::GameOver), despawn_game_over_text)
+        .add_systems(
+            OnEnter(AppState::Playing),
+            spawn_game.run_if(not(resource_exists::<ActiveRun>())),
+        )
+        .add_systems(Update, (transition_app_state, toggle_physics))
+        // These only read the rollback-synced `Transform`/`NetGameState` as
+        // `GgrsSchedule` left them for the frame. Running them in `Update`
+        // instead of `GgrsSchedule` keeps the cosmetic wall-clock timers
+        // (`despawn_expired_debris`) and RNG (`move_camera_to_ball`'s shake
+        // jitter) out of the rollback-replayed path, where neither would be
+        // deterministic across peers, and keeps `apply_game_over_transition`'s
+        // state change from being replayed (see its doc).
+        .add_systems(
+            Update,
             (
-                handle_input,
                 move_camera_to_ball,
+                despawn_expired_debris,
+                apply_game_over_transition,
+            )
+                .run_if(in_state(AppState::Playing)),
+        )
+        .add_systems(
+            FixedUpdate,
+            handle_input
+                .run_if(in_state(AppState::Playing))
+                .run_if(not(resource_exists::<Session<GgrsConfig>>())),
+        )
+        .add_systems(
+            FixedUpdate,
+            (
                 increase_score_and_spawn_platforms,
                 update_score,
                 detect_fall,
                 detect_hit_obstacle,
-                reset,
-            ),
-        )
-        .run();
+                resolve_pending_game_over,
+            )
+                .run_if(in_state(AppState::Playing))
+                .run_if(not(resource_exists::<Session<GgrsConfig>>())),
+        );
+
+    if let Some(netcode_args) = netcode_args {
+        app.add_plugins(GgrsPlugin::<GgrsConfig>::default())
+            .set_rollback_schedule_fps(60)
+            .rollback_component_with_copy::<Transform>()
+            .rollback_component_with_copy::<Velocity>()
+            .rollback_component_with_copy::<Score>()
+            .rollback_component_with_copy::<Scored>()
+            .rollback_component_with_copy::<NetGameState>()
+            .add_systems(ReadInputs, netcode::read_local_inputs)
+            .add_systems(
+                GgrsSchedule,
+                (
+                    netcode::apply_box_inputs,
+                    increase_score_and_spawn_platforms,
+                    update_score,
+                    detect_fall,
+                    detect_hit_obstacle,
+                    resolve_pending_game_over,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::Playing)),
+            )
+            .insert_resource(netcode::build_session(&netcode_args));
+    }
+
+    app.run();
 }
@@ -0,0 +1,131 @@
+use std::{collections::HashMap, env, net::SocketAddr};
+
+use bevy::prelude::{Commands, Component, Input, KeyCode, Query, Res};
+use bevy_ggrs::{ggrs, LocalInputs, LocalPlayers, PlayerInputs};
+use bevy_rapier3d::prelude::Velocity;
+
+use crate::config::Settings;
+
+pub const INPUT_LEFT: u8 = 1 << 0;
+pub const INPUT_RIGHT: u8 = 1 << 1;
+pub const INPUT_RESET: u8 = 1 << 2;
+
+/// Tags a `Ball` with the GGRS player handle that controls it. Handle `0` is
+/// always the locally-controlled ball in single-player games.
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub struct Player(pub usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+pub struct BoxInput {
+    pub buttons: u8,
+}
+
+#[derive(Debug)]
+pub struct GgrsConfig;
+
+impl ggrs::Config for GgrsConfig {
+    type Input = BoxInput;
+    type State = u8;
+    type Address = SocketAddr;
+}
+
+/// Parsed from `--local-port <port>` and one or more `--player <local|addr:port>`
+/// flags, e.g. `gradient --local-port 7000 --player local --player 203.0.113.4:7001`.
+pub struct NetcodeArgs {
+    pub local_port: u16,
+    pub players: Vec<String>,
+}
+
+pub fn parse_cli() -> Option<NetcodeArgs> {
+    let mut args = env::args().skip(1);
+    let mut local_port = None;
+    let mut players = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--local-port" => {
+                local_port = Some(
+                    args.next()
+                        .and_then(|value| value.parse().ok())
+                        .expect("--local-port requires a port number"),
+                );
+            }
+            "--player" => {
+                players.push(
+                    args.next()
+                        .expect("--player requires \"local\" or a remote address"),
+                );
+            }
+            _ => {}
+        }
+    }
+    if players.is_empty() {
+        return None;
+    }
+    Some(NetcodeArgs {
+        local_port: local_port.unwrap_or(7000),
+        players,
+    })
+}
+
+pub fn build_session(netcode_args: &NetcodeArgs) -> ggrs::P2PSession<GgrsConfig> {
+    let mut builder = ggrs::SessionBuilder::<GgrsConfig>::new()
+        .with_num_players(netcode_args.players.len());
+    for (handle, player) in netcode_args.players.iter().enumerate() {
+        builder = builder
+            .add_player(
+                if player == "local" {
+                    ggrs::PlayerType::Local
+                } else {
+                    ggrs::PlayerType::Remote(
+                        player.parse().expect("player address must be host:port"),
+                    )
+                },
+                handle,
+            )
+            .expect("failed to add player to GGRS session");
+    }
+    let socket = bevy_ggrs::UdpNonBlockingSocket::bind_to_port(netcode_args.local_port)
+        .expect("failed to bind GGRS UDP socket");
+    builder
+        .start_p2p_session(socket)
+        .expect("failed to start GGRS P2P session")
+}
+
+pub fn read_local_inputs(
+    local_players: Res<LocalPlayers>,
+    keyboard: Res<Input<KeyCode>>,
+    mut commands: Commands,
+) {
+    let mut local_inputs = HashMap::new();
+    for handle in &local_players.0 {
+        let mut buttons = 0;
+        if keyboard.any_pressed([KeyCode::Left, KeyCode::A]) {
+            buttons |= INPUT_LEFT;
+        }
+        if keyboard.any_pressed([KeyCode::Right, KeyCode::D]) {
+            buttons |= INPUT_RIGHT;
+        }
+        if keyboard.pressed(KeyCode::R) {
+            buttons |= INPUT_RESET;
+        }
+        local_inputs.insert(*handle, BoxInput { buttons });
+    }
+    commands.insert_resource(LocalInputs::<GgrsConfig>(local_inputs));
+}
+
+/// Applies each ball's rollback-synchronised [`BoxInput`] to its `Velocity`.
+/// Runs inside `GgrsSchedule` so the same frame number always yields the same
+/// world across peers.
+pub fn apply_box_inputs(
+    inputs: Res<PlayerInputs<GgrsConfig>>,
+    mut ball_query: Query<(&mut Velocity, &Player)>,
+    settings: Res<Settings>,
+) {
+    for (mut velocity, player) in &mut ball_query {
+        let (input, _) = inputs[player.0];
+        let horizontal =
+            f32::from(input.buttons & INPUT_RIGHT != 0) - f32::from(input.buttons & INPUT_LEFT != 0);
+        velocity.linvel.x += horizontal * settings.ball_control_force;
+    }
+}
@@ -0,0 +1,121 @@
+use bevy::{
+    asset::LoadState,
+    core_pipeline::Skybox,
+    pbr::{FogFalloff, FogSettings},
+    prelude::{
+        AssetServer, Assets, Color, Commands, Entity, Handle, Image, Query, Res, ResMut, Resource,
+        Transform, With,
+    },
+    render::render_resource::{TextureViewDescriptor, TextureViewDimension},
+};
+use bevy_rapier3d::prelude::Velocity;
+
+use crate::{netcode::Player, Ball, MainCamera};
+
+/// Themeable per-run look: which cubemap to wrap the scene in and what the
+/// gradient sky fades between as the ball dives deeper/faster.
+#[derive(Resource, Clone)]
+pub struct SkySettings {
+    pub skybox_image: String,
+    pub near_color: Color,
+    pub far_color: Color,
+}
+
+impl Default for SkySettings {
+    fn default() -> Self {
+        Self {
+            skybox_image: "skybox/stars.ktx2".to_string(),
+            near_color: Color::rgb(0.02, 0.02, 0.08),
+            far_color: Color::rgb(0.3, 0.05, 0.4),
+        }
+    }
+}
+
+#[derive(Resource)]
+struct SkyboxCubemap {
+    image: Handle<Image>,
+    is_loaded: bool,
+}
+
+pub fn load_skybox(mut commands: Commands, asset_server: Res<AssetServer>, sky: Res<SkySettings>) {
+    commands.insert_resource(SkyboxCubemap {
+        image: asset_server.load(&sky.skybox_image),
+        is_loaded: false,
+    });
+}
+
+/// The `Skybox` render pass fully replaces the background, so a gradient
+/// written to `ClearColor` would never be seen behind it. `update_gradient_sky`
+/// instead drives `FogSettings`, which tints the track itself and isn't
+/// occluded by the skybox, so the atmosphere still reads as layered in front
+/// of the stars.
+pub fn attach_skybox_to_camera(
+    mut commands: Commands,
+    camera_query: Query<Entity, With<MainCamera>>,
+    cubemap: Res<SkyboxCubemap>,
+    sky: Res<SkySettings>,
+) {
+    if let Ok(camera) = camera_query.get_single() {
+        commands.entity(camera).insert((
+            Skybox {
+                image: cubemap.image.clone(),
+                brightness: 1000.,
+            },
+            FogSettings {
+                color: sky.near_color,
+                falloff: FogFalloff::Linear {
+                    start: 50.,
+                    end: 500.,
+                },
+                ..Default::default()
+            },
+        ));
+    }
+}
+
+/// `Skybox` expects a cube texture array, but the cubemap asset loads as a
+/// flat 2D image first. Reinterpret it as a `Cube` array view as soon as its
+/// bytes are in, same as upstream Bevy's skybox example.
+pub fn reinterpret_skybox_once_loaded(
+    asset_server: Res<AssetServer>,
+    mut images: ResMut<Assets<Image>>,
+    mut cubemap: ResMut<SkyboxCubemap>,
+) {
+    if cubemap.is_loaded || asset_server.load_state(&cubemap.image) != LoadState::Loaded {
+        return;
+    }
+    let image = images.get_mut(&cubemap.image).unwrap();
+    if image.texture_descriptor.array_layer_count() == 1 {
+        image.reinterpret_stacked_2d_as_array(image.height() / image.width());
+        image.texture_view_descriptor = Some(TextureViewDescriptor {
+            dimension: Some(TextureViewDimension::Cube),
+            ..Default::default()
+        });
+    }
+    cubemap.is_loaded = true;
+}
+
+/// Shifts the fog color with the local player's depth and speed, layering a
+/// cheap procedural atmosphere in front of the track. Driving `FogSettings`
+/// instead of `ClearColor` means the gradient is still visible even though
+/// the opaque star skybox occupies the entire background behind it.
+pub fn update_gradient_sky(
+    sky: Res<SkySettings>,
+    ball_query: Query<(&Transform, &Velocity, &Player), With<Ball>>,
+    mut fog_query: Query<&mut FogSettings, With<MainCamera>>,
+) {
+    let Some((transform, velocity, _)) = ball_query.iter().find(|(_, _, player)| player.0 == 0)
+    else {
+        return;
+    };
+    let Ok(mut fog) = fog_query.get_single_mut() else {
+        return;
+    };
+    let depth = (-transform.translation.z / 2000.).clamp(0., 1.);
+    let speed = (velocity.linvel.length() / 50.).clamp(0., 1.);
+    let blend = (depth + speed) / 2.;
+    let near = sky.near_color.as_rgba_f32();
+    let far = sky.far_color.as_rgba_f32();
+    let lerp = |index: usize| near[index].mul_add(1. - blend, far[index] * blend);
+    fog.color = Color::rgba(lerp(0), lerp(1), lerp(2), lerp(3));
+}
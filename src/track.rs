@@ -0,0 +1,82 @@
+use bevy::prelude::{Commands, Res, Resource};
+use noise::{NoiseFn, Perlin};
+
+/// Seeds the coherent-noise track generator. Re-running with the same seed
+/// reproduces an identical track, which the rollback race mode relies on to
+/// keep every peer's course in sync.
+#[derive(Resource, Clone, Copy, Default)]
+pub struct TrackSeed(pub u32);
+
+/// Samples a Perlin field for the track's shape (lateral offset and banking),
+/// a second, lower-frequency field for obstacle density, and a third used as
+/// a deterministic stand-in for `rand::random()` anywhere the result needs to
+/// replay identically across GGRS rollback (obstacle scatter, debris
+/// direction).
+#[derive(Resource)]
+pub struct TrackGenerator {
+    path: Perlin,
+    density: Perlin,
+    jitter: Perlin,
+}
+
+impl TrackGenerator {
+    pub fn new(seed: TrackSeed) -> Self {
+        Self {
+            path: Perlin::new(seed.0),
+            density: Perlin::new(seed.0.wrapping_add(1)),
+            jitter: Perlin::new(seed.0.wrapping_add(2)),
+        }
+    }
+
+    /// Lateral X offset and Z-axis banking angle (radians) for the platform
+    /// pair `distance` steps into the track. Amplitude scales with `score`
+    /// so the course tightens as the player progresses.
+    pub fn path_offset(&self, distance: f64, score: u32) -> (f32, f32) {
+        let amplitude = 1. + f64::from(score) / 20.;
+        let lateral = self.path.get([distance * 0.15, 0.]) * 10. * amplitude;
+        let bank = self.path.get([distance * 0.15, 100.]) * 15. * amplitude;
+        (lateral as f32, (bank as f32).to_radians())
+    }
+
+    /// `0..=1` multiplier for how densely obstacles cluster around the
+    /// platform pair `distance` steps in, so hard stretches separate from
+    /// calmer ones instead of every platform looking the same.
+    pub fn obstacle_density(&self, distance: f64) -> f32 {
+        (self.density.get([distance * 0.03, 0.]) * 0.5 + 0.5) as f32
+    }
+
+    /// Deterministic, `-1.0..=1.0` replacement for `rand::random()`. Every
+    /// peer that samples the same `(a, b)` gets the same value, so systems
+    /// that run inside `GgrsSchedule` can vary their output across ticks
+    /// without holding RNG state that rollback would need to snapshot.
+    pub fn jitter(&self, a: f64, b: f64) -> f32 {
+        // Offset off the integer lattice: Perlin noise is exactly 0 at whole
+        // coordinates, which is what callers like the obstacle scatter pass.
+        self.jitter.get([a + 0.5, b + 0.5]) as f32
+    }
+}
+
+pub fn build_track_generator(mut commands: Commands, seed: Res<TrackSeed>) {
+    commands.insert_resource(TrackGenerator::new(*seed));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_replays_identically() {
+        let a = TrackGenerator::new(TrackSeed(42));
+        let b = TrackGenerator::new(TrackSeed(42));
+        assert_eq!(a.path_offset(12.5, 3), b.path_offset(12.5, 3));
+        assert_eq!(a.obstacle_density(12.5), b.obstacle_density(12.5));
+        assert_eq!(a.jitter(12.5, 3.0), b.jitter(12.5, 3.0));
+    }
+
+    #[test]
+    fn different_seeds_diverge() {
+        let a = TrackGenerator::new(TrackSeed(1));
+        let b = TrackGenerator::new(TrackSeed(2));
+        assert_ne!(a.path_offset(12.5, 0), b.path_offset(12.5, 0));
+    }
+}